@@ -0,0 +1,126 @@
+//! Before/after comparison that drives the real unpack strategies the chunk0-5 change is about,
+//! instead of a hand-rolled stand-in: [`unpack::unpack_archive_individually`] (reopen the source
+//! archive once per extracted file, the pre-chunk0-5 behavior) versus
+//! [`unpack::unpack_archive_group`] (open each archive once, extract every file belonging to it
+//! in offset order, the chunk0-5 behavior) — both included directly from `src/unpack.rs`.
+//!
+//! This only measures wall time; it does not count syscalls itself. Wrap the invocation in
+//! `strace -c` to get real read/seek/open counts for each strategy:
+//!
+//!   strace -c -- cargo run --release --bin extract_bench -- before <archive_dir> <scratch_dir>
+//!   strace -c -- cargo run --release --bin extract_bench -- after  <archive_dir> <scratch_dir>
+//!
+//! Run with `cargo run --release --bin extract_bench -- before|after <archive_dir> <scratch_dir>`
+//! once the crate grows a `[[bin]]` manifest entry for it; until then this is a standalone
+//! reference implementation against a directory of real `.db`/`.xdb` archives. `scratch_dir` is
+//! created if missing and is where extracted files are written, each directly under
+//! `scratch_dir` by its descriptor name: the archive's `entry_point` root is not applied and no
+//! subdirectories are created beforehand.
+
+#[path = "../src/archive_header.rs"]
+mod archive_header;
+#[path = "../src/unpack.rs"]
+mod unpack;
+
+use std::env;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use archive_header::{read_file_descriptors, FileDescriptor};
+
+#[derive(Debug, Clone)]
+pub struct ShortArchiveHeader {
+    pub archive_path: PathBuf,
+    pub output_root_path: String,
+}
+
+fn find_archives(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .expect("Can't get directory contents")
+        .filter_map(|entry| {
+            let entry = entry.expect("Can't read directory entry");
+            let path = entry.path();
+
+            let is_archive = entry.file_type().expect("Can't get file type").is_file()
+                && path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.starts_with("db") || ext.starts_with("xdb"))
+                    .unwrap_or(false);
+
+            is_archive.then_some(path)
+        })
+        .collect()
+}
+
+fn read_descriptors(archive_path: &Path) -> Vec<FileDescriptor> {
+    let mut file = File::open(archive_path).expect("Archive can be opened for reading");
+
+    loop {
+        let mut chunk_id_buf = [0u8; 4];
+        if file.read_exact(&mut chunk_id_buf).is_err() {
+            return Vec::new();
+        }
+        let raw_chunk_id = u32::from_le_bytes(chunk_id_buf);
+
+        let mut chunk_size_buf = [0u8; 4];
+        file.read_exact(&mut chunk_size_buf).expect("Chunk must have a size field");
+        let chunk_size = u32::from_le_bytes(chunk_size_buf) as usize;
+
+        let chunk_id = raw_chunk_id & archive_header::CHUNK_ID_MASK;
+
+        if chunk_id == 0x1 || chunk_id == 0x86 {
+            let mut chunk_data = vec![0u8; chunk_size];
+            file.read_exact(&mut chunk_data).expect("Can't read descriptor chunk");
+
+            let mut reader = Cursor::new(chunk_data.as_slice());
+
+            return read_file_descriptors(&mut reader, encoding_rs::UTF_8)
+                .expect("Valid file descriptors chunk")
+                .into_values()
+                .collect();
+        }
+
+        file.seek(SeekFrom::Current(chunk_size as i64)).expect("Can't skip unknown chunk");
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let mode = env::args().nth(1).expect("Usage: extract_bench <before|after> <archive_dir> <scratch_dir>");
+    let archive_dir = env::args().nth(2).expect("Usage: extract_bench <before|after> <archive_dir> <scratch_dir>");
+    let scratch_dir = env::args().nth(3).expect("Usage: extract_bench <before|after> <archive_dir> <scratch_dir>");
+    let archive_dir = Path::new(&archive_dir);
+
+    std::fs::create_dir_all(&scratch_dir).expect("Can't create scratch directory");
+
+    let archives: Vec<_> = find_archives(archive_dir)
+        .into_iter()
+        .map(|path| {
+            let archive_header = Arc::new(ShortArchiveHeader { archive_path: path.clone(), output_root_path: String::new() });
+            let descriptors = read_descriptors(&path);
+            (archive_header, descriptors)
+        })
+        .collect();
+
+    let file_count: usize = archives.iter().map(|(_, descriptors)| descriptors.len()).sum();
+    eprintln!("Loaded {} archive(s), {} file descriptor(s)", archives.len(), file_count);
+
+    let lzo = minilzo_rs::LZO::init().unwrap();
+    let start = Instant::now();
+
+    for (archive_header, descriptors) in archives {
+        match mode.as_str() {
+            "before" => unpack::unpack_archive_individually(&lzo, scratch_dir.as_str(), &archive_header, descriptors).await,
+            "after" => {
+                let mut descriptors = descriptors;
+                descriptors.sort_by_key(|desc| desc.offset);
+                unpack::unpack_archive_group(&lzo, scratch_dir.as_str(), &archive_header, descriptors).await
+            }
+            other => panic!("Unknown mode '{other}', expected 'before' or 'after'"),
+        }
+    }
+
+    println!("{}: {:?}", mode, start.elapsed());
+}
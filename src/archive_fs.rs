@@ -0,0 +1,216 @@
+use std::cmp::min;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use fuser::{FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, Request};
+use lru::LruCache;
+
+use crate::archive_header::FileDescriptor;
+use crate::ShortArchiveHeader;
+
+const TTL: Duration = Duration::from_secs(1);
+const ROOT_INO: u64 = 1;
+const DECOMPRESSED_CACHE_SIZE: usize = 64;
+
+enum Inode {
+    Dir { parent: u64, children: HashMap<String, u64> },
+    File { archive_header: Arc<ShortArchiveHeader>, descriptor: FileDescriptor },
+}
+
+/// Read-only FUSE view over the merged contents of one or more archives, decompressing
+/// files lazily on `read()` instead of up front.
+pub struct ArchiveFs {
+    inodes: HashMap<u64, Inode>,
+    lzo: Arc<minilzo_rs::LZO>,
+    decompressed_cache: Mutex<LruCache<u64, Arc<Vec<u8>>>>,
+}
+
+impl ArchiveFs {
+    pub fn new(files_and_dirs: HashMap<Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor)>, lzo: Arc<minilzo_rs::LZO>) -> Self {
+        let mut inodes = HashMap::new();
+        inodes.insert(ROOT_INO, Inode::Dir { parent: ROOT_INO, children: HashMap::new() });
+
+        let mut next_ino = ROOT_INO + 1;
+
+        for (file_name, (archive_header, desc)) in files_and_dirs.into_iter() {
+            let full_path: PathBuf = [archive_header.output_root_path.as_str(), file_name.as_str()].iter().collect();
+            let components: Vec<String> = full_path.iter().map(|c| c.to_string_lossy().into_owned()).collect();
+
+            let Some((file_component, dir_components)) = components.split_last() else { continue; };
+
+            let mut parent_ino = ROOT_INO;
+            for component in dir_components {
+                parent_ino = get_or_create_dir(&mut inodes, &mut next_ino, parent_ino, component);
+            }
+
+            let file_ino = next_ino;
+            next_ino += 1;
+            inodes.insert(file_ino, Inode::File { archive_header: archive_header.clone(), descriptor: desc });
+
+            if let Some(Inode::Dir { children, .. }) = inodes.get_mut(&parent_ino) {
+                children.insert(file_component.clone(), file_ino);
+            }
+        }
+
+        Self {
+            inodes,
+            lzo,
+            decompressed_cache: Mutex::new(LruCache::new(NonZeroUsize::new(DECOMPRESSED_CACHE_SIZE).unwrap())),
+        }
+    }
+
+    fn attr_for(&self, ino: u64) -> FileAttr {
+        let (kind, size) = match &self.inodes[&ino] {
+            Inode::Dir { .. } => (FileType::Directory, 0u64),
+            Inode::File { descriptor, .. } => (FileType::RegularFile, descriptor.real_size as u64),
+        };
+
+        FileAttr {
+            ino,
+            size,
+            blocks: (size + 511) / 512,
+            atime: UNIX_EPOCH,
+            mtime: UNIX_EPOCH,
+            ctime: UNIX_EPOCH,
+            crtime: UNIX_EPOCH,
+            kind,
+            perm: if kind == FileType::Directory { 0o555 } else { 0o444 },
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+            blksize: 512,
+            flags: 0,
+        }
+    }
+
+    fn read_file(&self, ino: u64) -> Result<Arc<Vec<u8>>, i32> {
+        let mut cache = self.decompressed_cache.lock().unwrap();
+        if let Some(data) = cache.get(&ino) {
+            return Ok(data.clone());
+        }
+
+        let (archive_header, descriptor) = match self.inodes.get(&ino) {
+            Some(Inode::File { archive_header, descriptor }) => (archive_header, descriptor),
+            _ => return Err(libc::ENOENT),
+        };
+
+        let mut source_file = File::open(archive_header.archive_path.as_path()).map_err(|_| libc::EIO)?;
+        source_file.seek(SeekFrom::Start(descriptor.offset as u64)).map_err(|_| libc::EIO)?;
+
+        let data = if descriptor.real_size != descriptor.compressed_size {
+            let mut buf = vec![0u8; descriptor.compressed_size as usize];
+            source_file.read_exact(&mut buf).map_err(|_| libc::EIO)?;
+            self.lzo.decompress_safe(buf.as_slice(), descriptor.real_size as usize).map_err(|_| libc::EIO)?
+        } else {
+            let mut buf = vec![0u8; descriptor.real_size as usize];
+            source_file.read_exact(&mut buf).map_err(|_| libc::EIO)?;
+            buf
+        };
+
+        let data = Arc::new(data);
+        cache.put(ino, data.clone());
+        Ok(data)
+    }
+}
+
+fn get_or_create_dir(inodes: &mut HashMap<u64, Inode>, next_ino: &mut u64, parent_ino: u64, name: &str) -> u64 {
+    let existing = match inodes.get(&parent_ino) {
+        Some(Inode::Dir { children, .. }) => children.get(name).copied(),
+        _ => None,
+    };
+
+    if let Some(ino) = existing {
+        return ino;
+    }
+
+    let ino = *next_ino;
+    *next_ino += 1;
+    inodes.insert(ino, Inode::Dir { parent: parent_ino, children: HashMap::new() });
+
+    if let Some(Inode::Dir { children, .. }) = inodes.get_mut(&parent_ino) {
+        children.insert(name.to_string(), ino);
+    }
+
+    ino
+}
+
+impl Filesystem for ArchiveFs {
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let name = name.to_string_lossy();
+
+        let child_ino = match self.inodes.get(&parent) {
+            Some(Inode::Dir { children, .. }) => children.get(name.as_ref()).copied(),
+            _ => None,
+        };
+
+        match child_ino {
+            Some(ino) => reply.entry(&TTL, &self.attr_for(ino), 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        match self.inodes.get(&ino) {
+            Some(_) => reply.attr(&TTL, &self.attr_for(ino)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        let entries: Vec<(u64, FileType, String)> = match self.inodes.get(&ino) {
+            Some(Inode::Dir { parent, children }) => {
+                let mut entries = vec![
+                    (ino, FileType::Directory, ".".to_string()),
+                    (*parent, FileType::Directory, "..".to_string()),
+                ];
+                for (name, child_ino) in children {
+                    let kind = match self.inodes.get(child_ino) {
+                        Some(Inode::Dir { .. }) => FileType::Directory,
+                        _ => FileType::RegularFile,
+                    };
+                    entries.push((*child_ino, kind, name.clone()));
+                }
+                entries
+            }
+            _ => {
+                reply.error(libc::ENOENT);
+                return;
+            }
+        };
+
+        for (i, (child_ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(child_ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+
+        reply.ok();
+    }
+
+    fn read(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, size: u32, _flags: i32, _lock: Option<u64>, reply: ReplyData) {
+        match self.read_file(ino) {
+            Ok(data) => {
+                let offset = min(offset as usize, data.len());
+                let end = min(offset + size as usize, data.len());
+                reply.data(&data[offset..end]);
+            }
+            Err(err) => reply.error(err),
+        }
+    }
+}
+
+/// Mounts `files_and_dirs` as a read-only filesystem at `mountpoint`. Blocks until unmounted.
+pub fn mount(mountpoint: &str, files_and_dirs: HashMap<Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor)>, lzo: Arc<minilzo_rs::LZO>) {
+    let fs = ArchiveFs::new(files_and_dirs, lzo);
+
+    let options = vec![fuser::MountOption::RO, fuser::MountOption::FSName("stalker_archive".to_string())];
+
+    fuser::mount2(fs, mountpoint, &options).expect("Failed to mount archive filesystem");
+}
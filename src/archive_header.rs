@@ -1,10 +1,16 @@
 use std::collections::HashMap;
-use std::io::{Error, Read};
+use std::io::{Error, Read, Write};
 use std::io::ErrorKind::UnexpectedEof;
 use std::sync::Arc;
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use encoding_rs::{Encoding};
 
+pub const CHUNK_ID_COMPRESSED_MASK: u32 = 1 << 31;
+pub const CHUNK_ID_MASK: u32 = !(1 << 31);
+
+/// Size in bytes of the fixed-width fields of a descriptor record (`real_size`, `compressed_size`, `crc`, `offset`).
+const ELEMENTS_SIZE: u16 = 16;
+
 #[derive(Debug)]
 pub struct FileDescriptor {
     pub name: Arc<String>,
@@ -20,8 +26,6 @@ pub fn read_file_descriptors<T: Read>(reader: &mut T, encoding: &'static Encodin
     let mut name_buf = [0u8; 260 * 2]; // MAX_PATH * 2
 
     loop {
-        const ELEMENTS_SIZE: u16 = 16;
-
         let header_size = match reader.read_u16::<LittleEndian>() {
             Ok(data) => data,
             Err(err) if err.kind() == UnexpectedEof => break,
@@ -56,3 +60,35 @@ pub fn read_file_descriptors<T: Read>(reader: &mut T, encoding: &'static Encodin
 
     Ok(file_descriptors)
 }
+
+/// Serializes `descriptors` in on-disk order, mirroring the layout [`read_file_descriptors`] parses.
+pub fn write_file_descriptors<T: Write>(writer: &mut T, descriptors: &[FileDescriptor], encoding: &'static Encoding) -> Result<(), Error> {
+    for descriptor in descriptors {
+        let (name_bytes, _, had_errors) = encoding.encode(descriptor.name.as_str());
+
+        if had_errors {
+            panic!("Had errors encoding file name '{}'", descriptor.name);
+        }
+
+        let header_size = ELEMENTS_SIZE + u16::try_from(name_bytes.len()).expect("File name too long to encode");
+
+        writer.write_u16::<LittleEndian>(header_size)?;
+        writer.write_u32::<LittleEndian>(descriptor.real_size)?;
+        writer.write_u32::<LittleEndian>(descriptor.compressed_size)?;
+        writer.write_u32::<LittleEndian>(descriptor.crc)?;
+        writer.write_all(name_bytes.as_ref())?;
+        writer.write_u32::<LittleEndian>(descriptor.offset)?;
+    }
+
+    Ok(())
+}
+
+/// Byte size `write_file_descriptors` would produce for `descriptors`, i.e. the descriptor chunk's payload size.
+pub fn file_descriptors_byte_size(descriptors: &[FileDescriptor], encoding: &'static Encoding) -> usize {
+    descriptors.iter()
+        .map(|descriptor| {
+            let (name_bytes, _, _) = encoding.encode(descriptor.name.as_str());
+            2 + ELEMENTS_SIZE as usize + name_bytes.len()
+        })
+        .sum()
+}
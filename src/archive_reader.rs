@@ -8,10 +8,7 @@ use std::io::{Cursor, SeekFrom};
 use std::sync::Arc;
 use delharc::decode::{Decoder, Lh1Decoder};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
-use crate::archive_header::{FileDescriptor, read_file_descriptors};
-
-const CHUNK_ID_COMPRESSED_MASK: u32 = 1 << 31;
-const CHUNK_ID_MASK: u32 = !(1 << 31);
+use crate::archive_header::{CHUNK_ID_COMPRESSED_MASK, CHUNK_ID_MASK, FileDescriptor, read_file_descriptors};
 
 #[derive(Debug, Clone)]
 pub struct ArchiveHeader {
@@ -0,0 +1,145 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use encoding_rs::Encoding;
+use tokio::io::AsyncWriteExt;
+
+use crate::archive_header::{file_descriptors_byte_size, FileDescriptor, write_file_descriptors};
+
+const CHUNK_ID_METADATA: u32 = 666;
+const CHUNK_ID_FILE_DESCRIPTORS: u32 = 0x1;
+const CHUNK_ID_DATA: u32 = 0x2;
+
+/// Builds a STALKER `.db`/`.xdb` archive from a directory, mirroring the chunked layout
+/// [`crate::archive_reader::ArchiveReader`] parses.
+pub struct ArchiveWriter {
+    encoding: &'static Encoding,
+}
+
+impl ArchiveWriter {
+    pub fn new(encoding: &'static Encoding) -> Self {
+        Self { encoding }
+    }
+
+    pub async fn write_archive(&self, input_dir: &Path, output_archive_path: &Path, entry_point_root: &str, compress_descriptors: bool) -> std::io::Result<()> {
+        if compress_descriptors {
+            // Checked before anything is read or written so a rejected request never leaves a
+            // truncated archive on disk. delharc only implements LHA decoding, not encoding, so
+            // compressed descriptor chunks can't be produced yet; uncompressed ones are fully supported.
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "--compress-descriptors is not supported yet: delharc has no LH encoder"));
+        }
+
+        let lzo = minilzo_rs::LZO::init().unwrap();
+
+        let mut relative_paths = Vec::new();
+        collect_files(input_dir, &PathBuf::new(), &mut relative_paths)?;
+        relative_paths.sort();
+
+        let mut descriptors = Vec::with_capacity(relative_paths.len());
+        let mut payloads = Vec::with_capacity(relative_paths.len());
+
+        for relative_path in relative_paths {
+            let absolute_path = input_dir.join(&relative_path);
+            let raw_bytes = tokio::fs::read(&absolute_path).await?;
+
+            let crc = crc32fast::hash(raw_bytes.as_slice());
+            let real_size = u32::try_from(raw_bytes.len()).expect("File too large to fit in a u32 size field");
+
+            let (payload, compressed_size) = match lzo.compress(raw_bytes.as_slice()) {
+                Ok(compressed) if compressed.len() < raw_bytes.len() => {
+                    let compressed_size = u32::try_from(compressed.len()).expect("Compressed data too large to fit in a u32 size field");
+                    (compressed, compressed_size)
+                }
+                _ => (raw_bytes, real_size),
+            };
+
+            let name = Arc::new(relative_path.to_string_lossy().replace('/', "\\"));
+
+            descriptors.push(FileDescriptor { name, offset: 0, real_size, compressed_size, crc });
+            payloads.push(payload);
+        }
+
+        let mut file = tokio::fs::File::options()
+            .read(false)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(output_archive_path).await?;
+
+        self.write_metadata_chunk(&mut file, entry_point_root).await?;
+
+        let data_chunk_header_size = 8u32;
+        let descriptor_chunk_header_size = 8u32;
+        let metadata_written = self.metadata_chunk_size(entry_point_root) + 8;
+
+        let mut running_offset = metadata_written as u32 + descriptor_chunk_header_size + file_descriptors_byte_size(&descriptors, self.encoding) as u32 + data_chunk_header_size;
+
+        for (descriptor, payload) in descriptors.iter_mut().zip(payloads.iter()) {
+            descriptor.offset = running_offset;
+            running_offset += u32::try_from(payload.len()).unwrap();
+        }
+
+        self.write_descriptor_chunk(&mut file, &descriptors).await?;
+
+        let data_chunk_size: u32 = payloads.iter().map(|p| u32::try_from(p.len()).unwrap()).sum();
+        file.write_u32_le(CHUNK_ID_DATA).await?;
+        file.write_u32_le(data_chunk_size).await?;
+        for payload in &payloads {
+            file.write_all(payload.as_slice()).await?;
+        }
+
+        Ok(())
+    }
+
+    fn metadata_chunk_size(&self, entry_point_root: &str) -> usize {
+        let text = self.header_ini_text(entry_point_root);
+        let (bytes, _, _) = self.encoding.encode(text.as_str());
+        bytes.len()
+    }
+
+    fn header_ini_text(&self, entry_point_root: &str) -> String {
+        format!("[header]\r\nentry_point = $game_data$\\{entry_point_root}\r\n")
+    }
+
+    async fn write_metadata_chunk(&self, file: &mut tokio::fs::File, entry_point_root: &str) -> std::io::Result<()> {
+        let text = self.header_ini_text(entry_point_root);
+        let (bytes, _, had_errors) = self.encoding.encode(text.as_str());
+
+        if had_errors {
+            panic!("Had errors encoding archive header with entry_point root '{}'", entry_point_root);
+        }
+
+        file.write_u32_le(CHUNK_ID_METADATA).await?;
+        file.write_u32_le(u32::try_from(bytes.len()).unwrap()).await?;
+        file.write_all(bytes.as_ref()).await?;
+
+        Ok(())
+    }
+
+    async fn write_descriptor_chunk(&self, file: &mut tokio::fs::File, descriptors: &[FileDescriptor]) -> std::io::Result<()> {
+        let mut buf = Cursor::new(Vec::new());
+        write_file_descriptors(&mut buf, descriptors, self.encoding)?;
+        let raw_bytes = buf.into_inner();
+
+        file.write_u32_le(CHUNK_ID_FILE_DESCRIPTORS).await?;
+        file.write_u32_le(u32::try_from(raw_bytes.len()).unwrap()).await?;
+        file.write_all(raw_bytes.as_slice()).await?;
+
+        Ok(())
+    }
+}
+
+fn collect_files(root: &Path, relative_dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root.join(relative_dir))? {
+        let entry = entry?;
+        let relative_path = relative_dir.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &relative_path, out)?;
+        } else {
+            out.push(relative_path);
+        }
+    }
+
+    Ok(())
+}
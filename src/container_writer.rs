@@ -0,0 +1,58 @@
+use std::io::Cursor;
+use std::path::Path;
+use tokio::sync::Mutex;
+
+/// Destination for `--archive-output` extraction: every file is appended to a single tar or
+/// zip container instead of being written as a loose file on disk. The underlying writer is
+/// mutex-guarded so concurrent unpack tasks can still share one sequential container.
+pub enum ContainerWriter {
+    Tar(Mutex<tokio_tar::Builder<tokio::fs::File>>),
+    Zip(Mutex<zip::ZipWriter<std::fs::File>>),
+}
+
+impl ContainerWriter {
+    pub async fn create(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("zip") => {
+                let file = std::fs::File::create(path).expect("Archive output file can be created");
+                ContainerWriter::Zip(Mutex::new(zip::ZipWriter::new(file)))
+            }
+            _ => {
+                let file = tokio::fs::File::create(path).await.expect("Archive output file can be created");
+                ContainerWriter::Tar(Mutex::new(tokio_tar::Builder::new(file)))
+            }
+        }
+    }
+
+    pub async fn append(&self, entry_path: &str, data: &[u8]) {
+        match self {
+            ContainerWriter::Tar(builder) => {
+                let mut header = tokio_tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+
+                builder.lock().await
+                    .append_data(&mut header, entry_path, Cursor::new(data)).await
+                    .expect("Unable to append file to tar archive");
+            }
+            ContainerWriter::Zip(writer) => {
+                let mut writer = writer.lock().await;
+
+                writer.start_file(entry_path, zip::write::FileOptions::default()).expect("Unable to start zip entry");
+                std::io::Write::write_all(&mut *writer, data).expect("Unable to write zip entry data");
+            }
+        }
+    }
+
+    pub async fn finish(self) {
+        match self {
+            ContainerWriter::Tar(builder) => {
+                builder.into_inner().finish().await.expect("Unable to finalize tar archive");
+            }
+            ContainerWriter::Zip(writer) => {
+                writer.into_inner().finish().expect("Unable to finalize zip archive");
+            }
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use std::cmp::{min};
 use std::collections::{HashMap, HashSet};
 use std::io::{SeekFrom};
 use std::io::ErrorKind::AlreadyExists;
@@ -16,15 +15,35 @@ use std_err_logger::StdErrLogger;
 use crate::archive_header::FileDescriptor;
 use crate::archive_reader::ArchiveHeader;
 
+mod archive_fs;
 mod archive_header;
 mod archive_reader;
+mod archive_writer;
+mod container_writer;
 mod std_err_logger;
+mod unpack;
 
 
 #[derive(Subcommand, Debug, Clone)]
 enum InputTypes {
     Archives { input: Vec<String> },
     Dirs { input: Vec<String> },
+    /// Mount the merged contents of one or more archives as a read-only FUSE filesystem
+    Mount { input: Vec<String> },
+    /// Build a new archive from a directory
+    Pack {
+        input: String,
+        #[arg(long, help = "Root the game should resolve this archive's files from, e.g. \"gamedata\\\". Written as entry_point = $game_data$\\<this> in the archive header.")]
+        entry_point_root: String,
+        #[arg(long, default_value_t = false, help = "LH-compress the file descriptor chunk (not yet supported: delharc has no LH encoder, so passing this always fails)")]
+        compress_descriptors: bool,
+    },
+    /// Print the merged archive index (after override resolution and --include/--exclude filtering) without extracting anything
+    List {
+        input: Vec<String>,
+        #[arg(long, default_value_t = false, help = "Print machine-readable (tab-separated) output")]
+        machine_readable: bool,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -33,8 +52,11 @@ struct Args {
     #[command(subcommand)]
     mode: InputTypes,
 
-    #[arg(short, long, help = "Output directory")]
-    output_dir: String,
+    #[arg(short, long, help = "Output directory. For the mount subcommand, this is the mountpoint instead; for the pack subcommand, the archive file to create. Required unless --archive-output is used.", conflicts_with = "archive_output")]
+    output_dir: Option<String>,
+
+    #[arg(short = 'a', long, help = "Write extracted files into a single .tar or .zip container (format inferred from the extension) instead of loose files on disk.", conflicts_with = "output_dir")]
+    archive_output: Option<String>,
 
     #[arg(short, long, default_value = "utf-8", help = "Encoding to use. For cases when archive contains non-ascii symbols in file names and headers. Examples: \"utf-8\", \"cp1251\"")]
     encoding: String,
@@ -44,6 +66,12 @@ struct Args {
 
     #[arg(short, long, default_value_t = LevelFilter::Warn, help = "Sets logging level for debug purposes")]
     log_level: LevelFilter,
+
+    #[arg(long, help = "Only keep files whose path matches this glob (e.g. \"textures/**\"). Can be passed multiple times; a file is kept if it matches any --include.")]
+    include: Vec<String>,
+
+    #[arg(long, help = "Drop files whose path matches this glob (e.g. \"*.ogg\"). Can be passed multiple times. Applied after --include.")]
+    exclude: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -63,10 +91,25 @@ async fn main() {
 
     let start_instant = Instant::now();
 
+    if let InputTypes::Pack { input, entry_point_root, compress_descriptors } = &args.mode {
+        let output_archive_path = args.output_dir.as_deref().expect("--output-dir (the archive file to create) is required for the pack subcommand");
+
+        info!("Packing {} into {}", input, output_archive_path);
+
+        let archive_writer = archive_writer::ArchiveWriter::new(encoding);
+        archive_writer.write_archive(PathBuf::from(input).as_path(), PathBuf::from(output_archive_path).as_path(), entry_point_root.as_str(), *compress_descriptors)
+            .await.expect("Failed to write archive");
+
+        eprintln!("Done. Took {} sec", start_instant.elapsed().as_secs_f32());
+        return;
+    }
+
     let archive_reader = Arc::new(ArchiveReader::new(encoding));
 
+    let mode = args.mode.clone();
+
     let files: Vec<_> = match args.mode {
-        InputTypes::Archives { input } => {
+        InputTypes::Archives { input } | InputTypes::Mount { input } | InputTypes::List { input, .. } => {
             input
                 .iter().map(|file| PathBuf::from(file))
                 .collect()
@@ -96,6 +139,7 @@ async fn main() {
             })
                 .collect()
         }
+        InputTypes::Pack { .. } => unreachable!("Pack mode is handled earlier and returns before this point"),
     };
 
     info!("Reading archive headers");
@@ -127,47 +171,101 @@ async fn main() {
         }
     }
 
+    if !args.include.is_empty() || !args.exclude.is_empty() {
+        info!("Applying --include/--exclude filters");
+        files_and_dirs = filter_by_glob(files_and_dirs, &args.include, &args.exclude);
+    }
+
+    if let InputTypes::List { machine_readable, .. } = &mode {
+        for (file_name, (archive_header, desc)) in files_and_dirs.iter() {
+            let full_path = normalized_full_path(archive_header, file_name.as_str());
+            let archive_name = archive_header.archive_path.file_name().unwrap().to_string_lossy();
+
+            if *machine_readable {
+                println!("{}\t{}\t{}\t{:08x}\t{}", full_path, desc.real_size, desc.compressed_size, desc.crc, archive_name);
+            } else {
+                println!("{:>10} {:>10} {:08x}  {}  [{}]", desc.real_size, desc.compressed_size, desc.crc, full_path, archive_name);
+            }
+        }
+
+        return;
+    }
+
+    if let InputTypes::Mount { .. } = &mode {
+        let mountpoint = args.output_dir.clone().expect("--output-dir (the mountpoint) is required for the mount subcommand");
+
+        info!("Mounting archive filesystem at {}", mountpoint);
+
+        let lzo = Arc::new(minilzo_rs::LZO::init().unwrap());
+
+        tokio::task::spawn_blocking(move || archive_fs::mount(mountpoint.as_str(), files_and_dirs, lzo))
+            .await.expect("Mount task panicked");
+
+        return;
+    }
+
+    let lzo = Arc::new(minilzo_rs::LZO::init().unwrap());
+
+    if let Some(archive_output_path) = &args.archive_output {
+        info!("Streaming files into {}", archive_output_path);
+
+        let container = Arc::new(container_writer::ContainerWriter::create(PathBuf::from(archive_output_path).as_path()).await);
+
+        let files_only = files_and_dirs.into_iter().filter(|(_, (_, desc))| desc.real_size != 0);
+
+        let mut tasks_set = bounded_join_set::JoinSet::new(64);
+
+        files_only.into_iter().for_each(|(_file_name, (archive_header, desc))| {
+            let container = container.clone();
+            let lzo = lzo.clone();
+            tasks_set.spawn(async move {
+                unpack_file_to_container(&lzo, &container, &archive_header, &desc).await
+            });
+        });
+
+        while tasks_set.join_next().await.is_some() {}
+
+        let container = Arc::try_unwrap(container).ok().expect("Container writer still has outstanding references");
+        container.finish().await;
+
+        info!("Total files: {total_file_count}");
+        eprintln!("Done. Took {} sec", start_instant.elapsed().as_secs_f32());
+        return;
+    }
+
+    let output_dir = args.output_dir.clone().expect("--output-dir is required unless --archive-output is used");
+
     info!("Creating directory structure");
 
-    create_directory_structure(&args.output_dir, &mut files_and_dirs).await;
+    create_directory_structure(&output_dir, &mut files_and_dirs).await;
 
     info!("Unpacking files");
 
     let parallel = !args.sequential;
 
-    let output_dir = Arc::new(args.output_dir.clone());
+    let output_dir = Arc::new(output_dir);
 
     let files_only = files_and_dirs.into_iter().filter(|(_, (_, desc))| desc.real_size != 0);
 
-    let lzo = Arc::new(minilzo_rs::LZO::init().unwrap());
+    let archive_groups = unpack::group_by_archive(files_only);
 
     match parallel {
         true => {
             let mut tasks_set = bounded_join_set::JoinSet::new(64);
 
-            files_only.into_iter().for_each(|(_file_name, (archive_header, desc))| {
+            archive_groups.into_iter().for_each(|(archive_header, descriptors)| {
                 let output_dir = output_dir.clone();
                 let lzo = lzo.clone();
                 tasks_set.spawn(async move {
-                    unpack_file(&lzo, output_dir.as_str(), &archive_header, &desc).await
+                    unpack::unpack_archive_group(&lzo, output_dir.as_str(), &archive_header, descriptors).await
                 });
             });
 
             while tasks_set.join_next().await.is_some() {}
-
-            // let unpack_tasks: Vec<_> = files_only.into_iter().map(|(_file_name, (archive_header, desc))| {
-            //     let output_dir = output_dir.clone();
-            //     let lzo = lzo.clone();
-            //     tokio::spawn(async move {
-            //         unpack_file(&lzo, output_dir.as_str(), &archive_header, &desc).await
-            //     })
-            // }).collect();
-            //
-            // join_all(unpack_tasks).await;
         }
         false => {
-            for (_file_name, (archive_header, desc)) in files_only.into_iter() {
-                unpack_file(&lzo, output_dir.clone().as_str(), &archive_header, &desc).await
+            for (archive_header, descriptors) in archive_groups.into_iter() {
+                unpack::unpack_archive_group(&lzo, output_dir.as_str(), &archive_header, descriptors).await
             }
         }
     }
@@ -245,8 +343,94 @@ async fn read_headers(archive_reader: Arc<ArchiveReader>, files: Vec<PathBuf>, s
     }
 }
 
-async fn unpack_file(lzo: &minilzo_rs::LZO, output_dir: &str, archive_header: &ShortArchiveHeader, file_descriptor: &FileDescriptor) {
-    let absolute_path: PathBuf = [output_dir, archive_header.output_root_path.as_str(), file_descriptor.name.as_str()].into_iter().collect();
+/// Archive-internal names use the Windows `\` separator (see `archive_writer::write_archive`),
+/// but on Unix a `/` in a `glob::Pattern` only matches a literal `/`, never `\`. Normalize to
+/// `/` before matching or displaying so a glob like `textures/**` and the paths printed by the
+/// `list` subcommand agree on the same string.
+fn normalized_path(file_name: &str) -> String {
+    file_name.replace('\\', "/")
+}
+
+fn normalized_full_path(archive_header: &ShortArchiveHeader, file_name: &str) -> String {
+    // output_root_path commonly retains its trailing separator (e.g. "levels\" once the reader
+    // strips the "$game_data$\" prefix) — trim it so the join below doesn't double up the slash.
+    let root = normalized_path(archive_header.output_root_path.as_str());
+    let root = root.trim_end_matches('/');
+    let name = normalized_path(file_name);
+
+    match root.is_empty() {
+        true => name,
+        false => format!("{root}/{name}"),
+    }
+}
+
+fn filter_by_glob(files_and_dirs: HashMap<Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor)>, include: &[String], exclude: &[String]) -> HashMap<Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor)> {
+    let include: Vec<_> = include.iter().map(|pattern| glob::Pattern::new(pattern).expect("Invalid --include glob")).collect();
+    let exclude: Vec<_> = exclude.iter().map(|pattern| glob::Pattern::new(pattern).expect("Invalid --exclude glob")).collect();
+
+    files_and_dirs.into_iter()
+        .filter(|(file_name, (archive_header, _))| {
+            let path = normalized_full_path(archive_header, file_name.as_str());
+
+            let included = include.is_empty() || include.iter().any(|pattern| pattern.matches(path.as_str()));
+            let excluded = exclude.iter().any(|pattern| pattern.matches(path.as_str()));
+
+            included && !excluded
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod glob_filter_tests {
+    use super::*;
+
+    fn descriptor(name: &str) -> (Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor)) {
+        let archive_header = Arc::new(ShortArchiveHeader {
+            archive_path: PathBuf::from("gamedata.db"),
+            output_root_path: String::new(),
+        });
+
+        let name = Arc::new(name.to_string());
+
+        (name.clone(), (archive_header, FileDescriptor { name, offset: 0, real_size: 1, compressed_size: 1, crc: 0 }))
+    }
+
+    #[test]
+    fn include_glob_matches_windows_separated_names() {
+        let files_and_dirs = HashMap::from([descriptor("textures\\foo.dds"), descriptor("sounds\\bar.ogg")]);
+
+        let filtered = filter_by_glob(files_and_dirs, &["textures/**".to_string()], &[]);
+
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered.contains_key(&Arc::new("textures\\foo.dds".to_string())));
+    }
+
+    #[test]
+    fn exclude_glob_matches_windows_separated_names() {
+        let files_and_dirs = HashMap::from([descriptor("textures\\foo.dds"), descriptor("sounds\\bar.ogg")]);
+
+        let filtered = filter_by_glob(files_and_dirs, &[], &["*.ogg".to_string()]);
+
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered.contains_key(&Arc::new("textures\\foo.dds".to_string())));
+    }
+
+    #[test]
+    fn full_path_does_not_double_slash_when_root_has_trailing_separator() {
+        let archive_header = Arc::new(ShortArchiveHeader {
+            archive_path: PathBuf::from("gamedata.db"),
+            output_root_path: "levels\\".to_string(),
+        });
+
+        assert_eq!(normalized_full_path(&archive_header, "level1\\foo.dds"), "levels/level1/foo.dds");
+    }
+}
+
+async fn unpack_file_to_container(lzo: &minilzo_rs::LZO, container: &container_writer::ContainerWriter, archive_header: &ShortArchiveHeader, file_descriptor: &FileDescriptor) {
+    // tar/zip entries are forward-slash paths on every platform; archive-internal names use the
+    // Windows '\' separator, so they must be normalized or entries extract as files/directories
+    // with literal backslashes in their names instead of the intended directory structure.
+    let entry_path = normalized_full_path(archive_header, file_descriptor.name.as_str());
 
     let mut source_file = tokio::fs::File::options()
         .read(true)
@@ -255,14 +439,7 @@ async fn unpack_file(lzo: &minilzo_rs::LZO, output_dir: &str, archive_header: &S
 
     source_file.seek(SeekFrom::Start(file_descriptor.offset as u64)).await.expect("Expected to be able to seek to start of the source file");
 
-    let mut dest_file = tokio::fs::File::options()
-        .read(false)
-        .write(true)
-        .create(true)
-        //.truncate(true)
-        .open(absolute_path).await.expect("File can be opened for writing");
-
-    if file_descriptor.real_size != file_descriptor.compressed_size {
+    let data = if file_descriptor.real_size != file_descriptor.compressed_size {
         let mut buf = vec![0u8; file_descriptor.compressed_size as usize];
         source_file.read_exact(buf.as_mut_slice()).await.unwrap();
 
@@ -272,23 +449,13 @@ async fn unpack_file(lzo: &minilzo_rs::LZO, output_dir: &str, archive_header: &S
 
         assert_eq!( file_descriptor.crc, actual_crc, "CRCs do not match");
 
-        dest_file.write_all(decompressed_buf.as_slice()).await.expect("Unable to write to dest file");
+        decompressed_buf
     } else {
-        let mut remaining_bytes = file_descriptor.real_size as usize;
-        let mut buf = vec![0u8;  min(256 * 1024, remaining_bytes)];
-        while remaining_bytes != 0 {
-            let to_read = min(buf.len(), remaining_bytes);
-            let read = source_file.read(&mut buf[..to_read]).await.unwrap();
-
-            assert!(read <= remaining_bytes, "Must not read more bytes than remaining");
-            assert_ne!(read, 0, "Unexpected End Of File");
-
-            dest_file.write(&buf[..read]).await.expect("Unable to write to destination file");
-            remaining_bytes -= read;
-        }
-    }
+        let mut buf = vec![0u8; file_descriptor.real_size as usize];
+        source_file.read_exact(buf.as_mut_slice()).await.unwrap();
 
-    dest_file.set_len(file_descriptor.real_size as u64).await.unwrap();
+        buf
+    };
 
-    // info!("{:?}", absolute_path);
+    container.append(entry_path.as_str(), data.as_slice()).await;
 }
\ No newline at end of file
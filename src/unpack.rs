@@ -0,0 +1,94 @@
+use std::cmp::min;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use std::io::SeekFrom;
+
+use crate::archive_header::FileDescriptor;
+use crate::ShortArchiveHeader;
+
+/// Buckets `files_only` by the archive they live in so each archive is opened once instead of
+/// once per file. Each group's descriptors are sorted by `offset` so [`unpack_archive_group`]
+/// can stream through the archive forward with minimal seeking.
+pub fn group_by_archive(files_only: impl Iterator<Item=(Arc<String>, (Arc<ShortArchiveHeader>, FileDescriptor))>) -> Vec<(Arc<ShortArchiveHeader>, Vec<FileDescriptor>)> {
+    let mut groups: HashMap<PathBuf, (Arc<ShortArchiveHeader>, Vec<FileDescriptor>)> = HashMap::new();
+
+    for (_file_name, (archive_header, desc)) in files_only {
+        groups.entry(archive_header.archive_path.clone())
+            .or_insert_with(|| (archive_header.clone(), Vec::new()))
+            .1.push(desc);
+    }
+
+    groups.into_values()
+        .map(|(archive_header, mut descriptors)| {
+            descriptors.sort_by_key(|desc| desc.offset);
+            (archive_header, descriptors)
+        })
+        .collect()
+}
+
+pub async fn unpack_archive_group(lzo: &minilzo_rs::LZO, output_dir: &str, archive_header: &ShortArchiveHeader, file_descriptors: Vec<FileDescriptor>) {
+    let mut source_file = tokio::fs::File::options()
+        .read(true)
+        .write(false)
+        .open(archive_header.archive_path.as_path()).await.expect("Archive can be opened for reading");
+
+    for file_descriptor in file_descriptors.iter() {
+        unpack_one_file(lzo, &mut source_file, output_dir, archive_header, file_descriptor).await;
+    }
+}
+
+/// Pre-chunk0-5 strategy kept for the `extract_bench` before/after comparison: reopens the
+/// archive once per file instead of grouping by archive first.
+pub async fn unpack_archive_individually(lzo: &minilzo_rs::LZO, output_dir: &str, archive_header: &ShortArchiveHeader, file_descriptors: Vec<FileDescriptor>) {
+    for file_descriptor in file_descriptors.iter() {
+        let mut source_file = tokio::fs::File::options()
+            .read(true)
+            .write(false)
+            .open(archive_header.archive_path.as_path()).await.expect("Archive can be opened for reading");
+
+        unpack_one_file(lzo, &mut source_file, output_dir, archive_header, file_descriptor).await;
+    }
+}
+
+async fn unpack_one_file(lzo: &minilzo_rs::LZO, source_file: &mut tokio::fs::File, output_dir: &str, archive_header: &ShortArchiveHeader, file_descriptor: &FileDescriptor) {
+    let absolute_path: PathBuf = [output_dir, archive_header.output_root_path.as_str(), file_descriptor.name.as_str()].into_iter().collect();
+
+    source_file.seek(SeekFrom::Start(file_descriptor.offset as u64)).await.expect("Expected to be able to seek to start of the source file");
+
+    let mut dest_file = tokio::fs::File::options()
+        .read(false)
+        .write(true)
+        .create(true)
+        //.truncate(true)
+        .open(absolute_path).await.expect("File can be opened for writing");
+
+    if file_descriptor.real_size != file_descriptor.compressed_size {
+        let mut buf = vec![0u8; file_descriptor.compressed_size as usize];
+        source_file.read_exact(buf.as_mut_slice()).await.unwrap();
+
+        let decompressed_buf = lzo.decompress_safe(buf.as_slice(), file_descriptor.real_size as usize).expect("Valid LZO data");
+
+        let actual_crc = crc32fast::hash(decompressed_buf.as_slice());
+
+        assert_eq!( file_descriptor.crc, actual_crc, "CRCs do not match");
+
+        dest_file.write_all(decompressed_buf.as_slice()).await.expect("Unable to write to dest file");
+    } else {
+        let mut remaining_bytes = file_descriptor.real_size as usize;
+        let mut buf = vec![0u8;  min(256 * 1024, remaining_bytes)];
+        while remaining_bytes != 0 {
+            let to_read = min(buf.len(), remaining_bytes);
+            let read = source_file.read(&mut buf[..to_read]).await.unwrap();
+
+            assert!(read <= remaining_bytes, "Must not read more bytes than remaining");
+            assert_ne!(read, 0, "Unexpected End Of File");
+
+            dest_file.write(&buf[..read]).await.expect("Unable to write to destination file");
+            remaining_bytes -= read;
+        }
+    }
+
+    dest_file.set_len(file_descriptor.real_size as u64).await.unwrap();
+}